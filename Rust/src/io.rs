@@ -0,0 +1,98 @@
+// Drives `Decoder` from a live byte stream, rather than a buffer that must
+// already hold the whole transmission, matching the "lazily received over
+// some connection" use case described in main.rs.
+
+use alloc::vec::Vec;
+
+use crate::yarhdlc::{Control, Decoder};
+
+/// A source of bytes `FrameReader` can pull from.
+///
+/// Implemented for `std::io::Read` behind the `std` feature and, when `std`
+/// is off, for `embedded_io::Read` behind the `embedded-io` feature, so the
+/// same `FrameReader` works both over a live socket/serial port and, without
+/// `std` but still with an allocator, over an embedded peripheral.
+pub trait ByteSource {
+    type Error;
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> ByteSource for R {
+    type Error = std::io::Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        std::io::Read::read(self, buf)
+    }
+}
+
+#[cfg(all(feature = "embedded-io", not(feature = "std")))]
+impl<R: embedded_io::Read> ByteSource for R {
+    type Error = R::Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(self, buf)
+    }
+}
+
+const REFILL_LEN: usize = 64;
+
+/// Iterates over the HDLC frames read from a [`ByteSource`].
+///
+/// `FrameReader` owns a small refill buffer and feeds bytes into a
+/// [`Decoder`] one at a time until a complete frame pops out. A bad FCS
+/// doesn't end the stream: since the decoder is always ready for the next
+/// frame as soon as it sees the closing flag, a failed frame is simply
+/// skipped and iteration resumes from the following `FLAG_SEQUENCE`.
+pub struct FrameReader<R: ByteSource> {
+    source: R,
+    decoder: Decoder<Vec<u8>>,
+    refill: [u8; REFILL_LEN],
+    pending: core::ops::Range<usize>,
+}
+
+impl<R: ByteSource> FrameReader<R> {
+    pub fn new(source: R) -> Self {
+        Self {
+            source,
+            decoder: Decoder::new(Vec::new()),
+            refill: [0; REFILL_LEN],
+            pending: 0..0,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, R::Error> {
+        if self.pending.is_empty() {
+            let read = self.source.read(&mut self.refill)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.pending = 0..read;
+        }
+        let byte = self.refill[self.pending.start];
+        self.pending.start += 1;
+        Ok(Some(byte))
+    }
+}
+
+impl<R: ByteSource> Iterator for FrameReader<R> {
+    type Item = Result<(Control, Vec<u8>), R::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let byte = match self.next_byte() {
+                Ok(Some(byte)) => byte,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match self.decoder.push(byte) {
+                None => continue,
+                Some(Err(_bad_frame)) => continue,
+                Some(Ok((control, payload))) => {
+                    return Some(Ok((control.clone(), payload.to_vec())))
+                }
+            }
+        }
+    }
+}
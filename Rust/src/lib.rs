@@ -0,0 +1,12 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod yarhdlc;
+
+#[cfg(feature = "alloc")]
+pub mod session;
+
+#[cfg(any(feature = "std", feature = "embedded-io"))]
+pub mod io;
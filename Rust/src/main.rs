@@ -1,4 +1,4 @@
-use veo_task::yarhdlc::{decode, FrameType, YahdlcError, FLAG_SEQUENCE};
+use veo_task::yarhdlc::{Decoder, Error as YahdlcError, FrameType, FLAG_SEQUENCE};
 
 // As the instructions allow for a small bit of interpretation,
 // I will describe my assumptions here.
@@ -53,29 +53,25 @@ impl Move {
     }
 }
 
-// The move iterator borrows the buffer containing all the received frames.
-// Calling next finds the next sequence enclosed by HDLC flag sequences on
-// both sides and feeds it to the decoder.
+// The move iterator borrows the buffer containing all the received frames
+// and feeds it byte by byte to a streaming Decoder, which finds the next
+// sequence enclosed by HDLC flag sequences on both sides.
 
 // Frames without any data (in this case ACK frames) are skipped.
 
-// The output buffer for decoding is owned by the iterator and is thus reused
+// The output buffer for decoding is owned by the Decoder and is thus reused
 // between calls to avoid repeated allocation.
 
 struct MoveIterator<'a> {
-    start: usize,
-    end: usize,
-    data: &'a [u8],
-    buffer: Vec<u8>,
+    data: core::slice::Iter<'a, u8>,
+    decoder: Decoder<Vec<u8>>,
 }
 
 impl<'a> MoveIterator<'a> {
     fn new(data: &'a [u8]) -> Result<Self, MoveIteratorError> {
         let out = Self {
-            start: 0,
-            end: 1,
-            data,
-            buffer: Vec::new(),
+            data: data.iter(),
+            decoder: Decoder::new(Vec::new()),
         };
         // Special case for empty slices - the iterator is valid by immediately returns None.
         if data.len() == 0 {
@@ -96,29 +92,24 @@ impl<'a> Iterator for MoveIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            // If get returns none there is no more data, and the iterator is done.
-            let byte = *self.data.get(self.end)?;
-            if byte == FLAG_SEQUENCE {
-                let control = match decode(&self.data[self.start..=self.end], &mut self.buffer){
-                    Ok(val) => val,
-                    Err(err) => return Some(Err(err.into()))
-                };
-                self.start = self.end + 1;
-                self.end += 2;
-                match control.frame_type {
-                    FrameType::Data => {
-                        let mv = match Move::from_u8(self.buffer[0]){
-                            Some(mv) => mv,
-                            None => return Some(Err(MoveIteratorError::InvalidMove))
-                        };
-                        self.buffer.clear();
-                        return Some(Ok(mv));
-                    }
-                    FrameType::Acknowledge => continue,
-                    FrameType::NegativeAcknowledge => continue,
-                };
-            } else {
-                self.end += 1;
+            // If next returns none there is no more data, and the iterator is done.
+            let byte = *self.data.next()?;
+            let (control, payload) = match self.decoder.push(byte) {
+                None => continue,
+                Some(Err(err)) => return Some(Err(err.into())),
+                Some(Ok(frame)) => frame,
+            };
+            match control.frame_type {
+                FrameType::Data => {
+                    let mv = match Move::from_u8(payload[0]) {
+                        Some(mv) => mv,
+                        None => return Some(Err(MoveIteratorError::InvalidMove)),
+                    };
+                    return Some(Ok(mv));
+                }
+                FrameType::Acknowledge => continue,
+                FrameType::NegativeAcknowledge => continue,
+                FrameType::SelectiveReject => continue,
             }
         }
     }
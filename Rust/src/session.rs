@@ -0,0 +1,275 @@
+// A Go-Back-N / selective-reject ARQ session layered over `yarhdlc`'s frame
+// codec, giving callers a reliable link out of the bare Data/Ack/Nack
+// control-byte layout.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::yarhdlc::{self, Control, Error, FrameType};
+
+const SEQ_MODULUS: u8 = 8;
+
+/// Largest number of Data frames that may be outstanding at once.
+///
+/// [`drop_acked_through`](Session::drop_acked_through) treats an ack whose
+/// distance from the oldest outstanding frame is `>= SEQ_MODULUS / 2` (that
+/// is, `>= SEND_WINDOW`) as stale rather than cumulative. The far end of a
+/// full window lies at distance `SEND_WINDOW - 1`, strictly below that
+/// threshold, so a legitimate ack for it is never mistaken for a stale one.
+const SEND_WINDOW: u8 = SEQ_MODULUS / 2;
+
+/// How many steps forward (mod [`SEQ_MODULUS`]) it takes to get from `from`
+/// to `to`.
+fn seq_distance(from: u8, to: u8) -> u8 {
+    (to + SEQ_MODULUS - from) % SEQ_MODULUS
+}
+
+/// Why [`Session::send`] could not encode and queue a Data frame.
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    /// [`SEND_WINDOW`] unacked frames are already outstanding; the caller
+    /// must wait for an ack before sending more; retransmits do not free
+    /// room in the window.
+    #[error("the send window is full ({SEND_WINDOW} frames outstanding)")]
+    WindowFull,
+
+    #[error(transparent)]
+    Encode(#[from] Error),
+}
+
+/// Something the caller of [`Session`] must do in response to a frame or a
+/// timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// A Data frame's payload, ready for the application.
+    Deliver(Vec<u8>),
+    /// Bytes that must be written to the link as-is.
+    Send(Vec<u8>),
+    /// The outbound sequence number whose frame must be re-encoded and sent
+    /// again.
+    Retransmit(u8),
+}
+
+/// Reliable link layer built on top of [`yarhdlc::encode`] and
+/// [`yarhdlc::Decoder`].
+///
+/// `Session` tracks outbound sequence numbers modulo 8 and which of them are
+/// still awaiting acknowledgement, and turns inbound frames into [`Action`]s:
+/// deliver a payload to the application, send an Ack/Nack back over the
+/// link, or retransmit an outstanding frame. The caller is expected to have
+/// kept the bytes `send` returned for a sequence number, since `Retransmit`
+/// only names the number, not the frame itself.
+#[derive(Debug, Default)]
+pub struct Session {
+    next_seq: u8,
+    expected_seq: u8,
+    unacked: VecDeque<u8>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encodes `payload` as the next outbound Data frame and records its
+    /// sequence number as outstanding until it is acknowledged. The encoded
+    /// bytes are returned to the caller and not kept by the session.
+    ///
+    /// Returns [`SendError::WindowFull`] once [`SEND_WINDOW`] frames are
+    /// outstanding; the caller should hold `payload` and retry after the
+    /// next ack.
+    pub fn send(&mut self, payload: &[u8]) -> Result<Vec<u8>, SendError> {
+        if self.unacked.len() >= SEND_WINDOW as usize {
+            return Err(SendError::WindowFull);
+        }
+
+        let seq = self.next_seq;
+        self.next_seq = (self.next_seq + 1) % SEQ_MODULUS;
+
+        let bytes = self.encode_data(seq, payload)?;
+        self.unacked.push_back(seq);
+
+        Ok(bytes)
+    }
+
+    /// Feeds a frame decoded off the link to the session, returning the
+    /// actions the caller must take in response.
+    pub fn on_frame(&mut self, control: Control, payload: &[u8]) -> Vec<Action> {
+        match control.frame_type {
+            FrameType::Data => self.on_data(control.sequence_no, payload),
+            FrameType::Acknowledge => {
+                self.drop_acked_through(control.sequence_no);
+                Vec::new()
+            }
+            FrameType::NegativeAcknowledge => self.retransmit_from(control.sequence_no),
+            FrameType::SelectiveReject => self.retransmit_one(control.sequence_no),
+        }
+    }
+
+    /// Actions to take for outbound frames still waiting on acknowledgement,
+    /// to be called whenever the caller's retransmission timer fires.
+    pub fn on_timeout(&mut self) -> Vec<Action> {
+        self.unacked.iter().copied().map(Action::Retransmit).collect()
+    }
+
+    fn on_data(&mut self, sequence_no: u8, payload: &[u8]) -> Vec<Action> {
+        let mut actions = Vec::new();
+        if sequence_no == self.expected_seq {
+            self.expected_seq = (self.expected_seq + 1) % SEQ_MODULUS;
+            actions.push(Action::Deliver(payload.to_vec()));
+            if let Ok(bytes) = self.encode_control(FrameType::Acknowledge, sequence_no) {
+                actions.push(Action::Send(bytes));
+            }
+        } else if let Ok(bytes) = self.encode_control(FrameType::NegativeAcknowledge, self.expected_seq) {
+            actions.push(Action::Send(bytes));
+        }
+        actions
+    }
+
+    fn drop_acked_through(&mut self, sequence_no: u8) {
+        let base = match self.unacked.front() {
+            Some(&seq) => seq,
+            None => return,
+        };
+        let target = seq_distance(base, sequence_no);
+        if target >= SEQ_MODULUS / 2 {
+            // Further behind than our oldest outstanding frame: a stale or
+            // out-of-range ack, not a valid cumulative ack. Ignore it
+            // instead of draining frames that haven't actually been
+            // acknowledged.
+            return;
+        }
+        while let Some(&seq) = self.unacked.front() {
+            if seq_distance(base, seq) > target {
+                break;
+            }
+            self.unacked.pop_front();
+        }
+    }
+
+    fn retransmit_from(&mut self, sequence_no: u8) -> Vec<Action> {
+        self.unacked
+            .iter()
+            .copied()
+            .skip_while(|&seq| seq != sequence_no)
+            .map(Action::Retransmit)
+            .collect()
+    }
+
+    /// Retransmits only the named frame, as opposed to
+    /// [`retransmit_from`](Self::retransmit_from)'s "it and everything
+    /// after it". Used for [`FrameType::SelectiveReject`], where the peer
+    /// has told us exactly which frame it is missing.
+    fn retransmit_one(&self, sequence_no: u8) -> Vec<Action> {
+        if self.unacked.contains(&sequence_no) {
+            alloc::vec![Action::Retransmit(sequence_no)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn encode_data(&self, seq: u8, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        let control = Control {
+            frame_type: FrameType::Data,
+            sequence_no: seq,
+        };
+        let mut bytes = Vec::new();
+        yarhdlc::encode(control, payload, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn encode_control(&self, frame_type: FrameType, sequence_no: u8) -> Result<Vec<u8>, Error> {
+        let control = Control {
+            frame_type,
+            sequence_no,
+        };
+        let mut bytes = Vec::new();
+        yarhdlc::encode(control, &[], &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yarhdlc::Decoder;
+
+    /// Decodes `wire` as if a peer had just received it and feeds every
+    /// resulting frame to `session`, returning the actions it produced.
+    fn relay(session: &mut Session, wire: &[u8]) -> Vec<Action> {
+        let mut decoder = Decoder::new(Vec::new());
+        let mut actions = Vec::new();
+        for &byte in wire {
+            if let Some(result) = decoder.push(byte) {
+                let (control, payload) = result.unwrap();
+                actions.extend(session.on_frame(control, payload));
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn loopback_delivers_payload_and_acknowledges() {
+        let mut sender = Session::new();
+        let mut receiver = Session::new();
+
+        let wire = sender.send(b"hello").unwrap();
+        let actions = relay(&mut receiver, &wire);
+
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0], Action::Deliver(b"hello".to_vec()));
+        let Action::Send(ack_bytes) = &actions[1] else {
+            panic!("expected an Ack to be sent back, got {:?}", actions[1]);
+        };
+
+        let actions = relay(&mut sender, ack_bytes);
+        assert!(actions.is_empty());
+        assert!(sender.unacked.is_empty());
+    }
+
+    #[test]
+    fn stale_ack_does_not_drain_outstanding_frames() {
+        let mut sender = Session::new();
+        sender.send(b"one").unwrap();
+        sender.send(b"two").unwrap();
+
+        // An ack that falls behind our oldest outstanding frame (here,
+        // wrapped almost all the way around) is stale and must not wipe
+        // out frames that were never actually acknowledged.
+        sender.drop_acked_through(7);
+
+        assert_eq!(sender.unacked, alloc::collections::VecDeque::from([0, 1]));
+    }
+
+    #[test]
+    fn send_rejects_once_window_is_full() {
+        let mut sender = Session::new();
+        for _ in 0..SEND_WINDOW {
+            sender.send(b"x").unwrap();
+        }
+
+        assert!(matches!(sender.send(b"x"), Err(SendError::WindowFull)));
+
+        // A legitimate cumulative ack for the far end of a full window must
+        // still be honored, not mistaken for a stale one.
+        sender.drop_acked_through(SEND_WINDOW - 1);
+        assert!(sender.unacked.is_empty());
+        assert!(sender.send(b"x").is_ok());
+    }
+
+    #[test]
+    fn selective_reject_retransmits_only_the_named_frame() {
+        let mut sender = Session::new();
+        sender.send(b"one").unwrap();
+        sender.send(b"two").unwrap();
+        sender.send(b"three").unwrap();
+
+        let control = Control {
+            frame_type: FrameType::SelectiveReject,
+            sequence_no: 1,
+        };
+        let actions = sender.on_frame(control, &[]);
+
+        assert_eq!(actions, alloc::vec![Action::Retransmit(1)]);
+    }
+}
@@ -1,8 +1,10 @@
 // A simple read-only Rust adaptation of yahdlc (https://github.com/bang-olufsen/yahdlc)
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 pub const FLAG_SEQUENCE: u8 = 0x7E;
 const CONTROL_ESCAPE: u8 = 0x7D;
-#[allow(unused)]
 const ALL_STATION_ADDR: u8 = 0xFF;
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -11,7 +13,6 @@ struct FrameCheckSequence(u16);
 impl FrameCheckSequence{
     const INIT_VALUE: Self = Self(0xFFFF);
     const GOOD_VALUE: Self = Self(0xF0B8);
-    #[allow(unused)]
     const INVERT_MASK: Self = Self(0xFFFF);
 
     const LOOKUP: [u16; 256] = [ 0x0000, 0x1189, 0x2312, 0x329b,
@@ -71,14 +72,18 @@ impl Default for State {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FrameType {
     Data,
     Acknowledge,
     NegativeAcknowledge,
+    /// A selective reject naming exactly one bad frame, as opposed to
+    /// [`NegativeAcknowledge`](Self::NegativeAcknowledge)'s "everything
+    /// from here on" reject.
+    SelectiveReject,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Control {
     pub frame_type: FrameType,
     pub sequence_no: u8,
@@ -107,14 +112,14 @@ impl From<ControlByte> for Control {
         let sequence_no;
         let frame_type;
         if value & (1 << ControlByte::S_OR_U) != 0 {
-            if ((value >> ControlByte::S_FRAME_TYPE) & 0x3) == ControlByte::RECEIVE_READY {
-                frame_type = FrameType::Acknowledge
-            } else {
-                frame_type = FrameType::NegativeAcknowledge
-            }
-            sequence_no = value >> ControlByte::SEND_SEQ_NO;
+            frame_type = match (value >> ControlByte::S_FRAME_TYPE) & 0x3 {
+                ControlByte::RECEIVE_READY => FrameType::Acknowledge,
+                ControlByte::SELECTIVE_REJECT => FrameType::SelectiveReject,
+                _ => FrameType::NegativeAcknowledge,
+            };
+            sequence_no = value >> ControlByte::RECV_SEQ_NO;
         } else {
-            sequence_no = value >> ControlByte::SEND_SEQ_NO;
+            sequence_no = (value >> ControlByte::SEND_SEQ_NO) & 0x7;
             frame_type = FrameType::Data
         };
         Self {
@@ -138,11 +143,84 @@ impl From<Control> for ControlByte {
                     | ControlByte::REJECT << ControlByte::S_FRAME_TYPE
                     | (1 << ControlByte::S_OR_U)
             }
+            FrameType::SelectiveReject => {
+                (value.sequence_no << ControlByte::RECV_SEQ_NO)
+                    | ControlByte::SELECTIVE_REJECT << ControlByte::S_FRAME_TYPE
+                    | (1 << ControlByte::S_OR_U)
+            }
         })
     }
 }
 
 
+/// Raised by a [`FrameSink`] when it has no room left for another byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("the output sink has no room left for more bytes")]
+pub struct Overflow;
+
+/// Destination for the payload bytes produced by [`decode`].
+///
+/// This lets `decode` run with no allocator: embedded callers can hand it a
+/// `&mut [u8]`-backed [`SliceSink`] sized to their largest expected frame,
+/// while callers with an allocator can keep using a `Vec<u8>` via the
+/// `alloc` feature.
+pub trait FrameSink {
+    /// Appends `byte`, returning [`Overflow`] if the sink is already full.
+    fn push(&mut self, byte: u8) -> Result<(), Overflow>;
+    /// Shrinks the sink to its first `n` pushed bytes.
+    fn truncate(&mut self, n: usize);
+}
+
+/// A [`FrameSink`] backed by a fixed-size buffer, for use without an allocator.
+#[derive(Debug)]
+pub struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    /// Wraps `buf` as an initially-empty sink.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// The bytes pushed so far.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl<'a> FrameSink for SliceSink<'a> {
+    fn push(&mut self, byte: u8) -> Result<(), Overflow> {
+        let slot = self.buf.get_mut(self.len).ok_or(Overflow)?;
+        *slot = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn truncate(&mut self, n: usize) {
+        self.len = self.len.min(n);
+    }
+}
+
+impl<'a> AsRef<[u8]> for SliceSink<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl FrameSink for Vec<u8> {
+    fn push(&mut self, byte: u8) -> Result<(), Overflow> {
+        Vec::push(self, byte);
+        Ok(())
+    }
+
+    fn truncate(&mut self, n: usize) {
+        Vec::truncate(self, n)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("The frame check sequence did not match that in the packet")]
@@ -153,13 +231,17 @@ pub enum Error {
 
     #[error("Any message should be greater than 4 bytes")]
     TooShort,
+
+    #[error("The output sink ran out of room while decoding the frame")]
+    BufferOverflow(#[from] Overflow),
 }
 
-pub fn decode(data: &[u8], output: &mut Vec<u8>) -> Result<Control, Error>{
+pub fn decode<S: FrameSink>(data: &[u8], output: &mut S) -> Result<Control, Error>{
     let mut state = State::default();
     let mut data_iter = data.iter().peekable();
     let mut value;
     let mut control: Option<Control> = None;
+    let mut pushed: usize = 0;
     while let Some(&byte) = data_iter.next(){
         if let Some(start_index) = state.start_index{
             if byte == FLAG_SEQUENCE{
@@ -187,7 +269,8 @@ pub fn decode(data: &[u8], output: &mut Vec<u8>) -> Result<Control, Error>{
                 if state.src_index == start_index + 2{
                     control = Some(ControlByte(byte).into())
                 } else if state.src_index > start_index + 2{
-                    output.push(value)
+                    output.push(value)?;
+                    pushed += 1;
                 }
             }
         } else {
@@ -202,9 +285,7 @@ pub fn decode(data: &[u8], output: &mut Vec<u8>) -> Result<Control, Error>{
     }
 
     // Remove the FCS from the output
-    for _ in 0..core::mem::size_of::<u16>(){
-        output.pop();
-    }
+    output.truncate(pushed.saturating_sub(core::mem::size_of::<u16>()));
 
     if let (Some(start), Some(end)) = (state.start_index, state.end_index){
         if end < start + 4{
@@ -220,4 +301,248 @@ pub fn decode(data: &[u8], output: &mut Vec<u8>) -> Result<Control, Error>{
     
 
     Ok(control.unwrap())
+}
+
+/// Incremental HDLC decoder fed one byte at a time.
+///
+/// Unlike [`decode`], which needs the whole frame resident in a single
+/// `&[u8]`, `Decoder` keeps its state across calls to [`push`](Self::push)
+/// so frames can be assembled straight from a UART/socket read loop without
+/// ever concatenating the stream.
+#[derive(Debug)]
+pub struct Decoder<S> {
+    in_frame: bool,
+    control_escape: bool,
+    fcs: FrameCheckSequence,
+    byte_index: usize,
+    pushed: usize,
+    control: Option<Control>,
+    output: S,
+}
+
+impl<S: FrameSink + AsRef<[u8]>> Decoder<S> {
+    /// Creates a decoder that accumulates frame payloads into `output`.
+    pub fn new(output: S) -> Self {
+        Self {
+            in_frame: false,
+            control_escape: false,
+            fcs: FrameCheckSequence::INIT_VALUE,
+            byte_index: 0,
+            pushed: 0,
+            control: None,
+            output,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.control_escape = false;
+        self.fcs = FrameCheckSequence::INIT_VALUE;
+        self.byte_index = 0;
+        self.pushed = 0;
+        self.control = None;
+    }
+
+    fn finish(&mut self) -> Result<Control, Error> {
+        if self.byte_index + 1 < 4 {
+            return Err(Error::TooShort);
+        }
+        if self.fcs != FrameCheckSequence::GOOD_VALUE {
+            return Err(Error::FrameCheckSequenceInvalid);
+        }
+        self.output
+            .truncate(self.pushed.saturating_sub(core::mem::size_of::<u16>()));
+        Ok(self.control.take().unwrap())
+    }
+
+    /// Feeds a single byte to the decoder.
+    ///
+    /// Returns `None` while a frame is still being assembled, and the
+    /// completed frame's control field and payload the moment a closing
+    /// `FLAG_SEQUENCE` is seen. Because the closing flag of one frame
+    /// doubles as the opening flag of the next, and a run of flags with
+    /// nothing in between is just padding, the decoder stays ready for
+    /// more frames after returning one. If the output sink runs out of room,
+    /// [`Error::BufferOverflow`] is returned at once and the rest of that
+    /// frame, up to the next `FLAG_SEQUENCE`, is dropped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<(Control, &[u8]), Error>> {
+        if !self.in_frame {
+            if byte == FLAG_SEQUENCE {
+                self.in_frame = true;
+            }
+            return None;
+        }
+
+        if byte == FLAG_SEQUENCE {
+            if self.byte_index == 0 {
+                // A run of leading (or redundant) flags: still waiting
+                // for the frame to start.
+                return None;
+            }
+
+            let result = self.finish();
+            // The closing flag doubles as the opening flag of the next
+            // frame, so we stay in-frame instead of leaving it.
+            self.reset();
+            return Some(result.map(|control| (control, self.output.as_ref())));
+        }
+
+        if self.byte_index == 0 {
+            self.output.truncate(0);
+        }
+
+        let value = if self.control_escape {
+            self.control_escape = false;
+            byte ^ 0x20
+        } else if byte == CONTROL_ESCAPE {
+            self.control_escape = true;
+            self.byte_index += 1;
+            return None;
+        } else {
+            byte
+        };
+
+        self.fcs.update(value);
+
+        if self.byte_index == 1 {
+            self.control = Some(ControlByte(byte).into());
+        } else if self.byte_index > 1 {
+            if let Err(err) = self.output.push(value) {
+                // Drop the rest of this frame; the next flag opens a new one.
+                self.in_frame = false;
+                self.reset();
+                return Some(Err(err.into()));
+            }
+            self.pushed += 1;
+        }
+
+        self.byte_index += 1;
+        None
+    }
+}
+
+fn push_escaped<S: FrameSink>(output: &mut S, byte: u8) -> Result<(), Error> {
+    if byte == FLAG_SEQUENCE || byte == CONTROL_ESCAPE {
+        output.push(CONTROL_ESCAPE)?;
+        output.push(byte ^ 0x20)?;
+    } else {
+        output.push(byte)?;
+    }
+    Ok(())
+}
+
+/// Encodes `payload` under `control` into an HDLC frame, the write-side
+/// counterpart to [`decode`].
+///
+/// Emits a leading `FLAG_SEQUENCE`, the station address, the control byte
+/// derived from `control`, `payload`, and the 16-bit FCS (CCITT, ones'
+/// complemented, low byte first) computed over the address, control byte
+/// and payload, byte-stuffing any `FLAG_SEQUENCE` or `CONTROL_ESCAPE` byte along the way,
+/// followed by a trailing `FLAG_SEQUENCE`. `decode(encode(c, p))` recovers
+/// `c` and `p` exactly.
+pub fn encode(control: Control, payload: &[u8], output: &mut impl FrameSink) -> Result<(), Error> {
+    let control_byte = ControlByte::from(control).0;
+    let mut fcs = FrameCheckSequence::INIT_VALUE;
+
+    output.push(FLAG_SEQUENCE)?;
+
+    fcs.update(ALL_STATION_ADDR);
+    push_escaped(output, ALL_STATION_ADDR)?;
+
+    fcs.update(control_byte);
+    push_escaped(output, control_byte)?;
+
+    for &byte in payload {
+        fcs.update(byte);
+        push_escaped(output, byte)?;
+    }
+
+    let fcs = fcs.0 ^ FrameCheckSequence::INVERT_MASK.0;
+    push_escaped(output, (fcs & 0xFF) as u8)?;
+    push_escaped(output, (fcs >> 8) as u8)?;
+
+    output.push(FLAG_SEQUENCE)?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn frame_types() -> [FrameType; 4] {
+        [
+            FrameType::Data,
+            FrameType::Acknowledge,
+            FrameType::NegativeAcknowledge,
+            FrameType::SelectiveReject,
+        ]
+    }
+
+    #[test]
+    fn round_trip_every_frame_type_and_sequence_number() {
+        let payload = [1u8, FLAG_SEQUENCE, CONTROL_ESCAPE, 9];
+        for frame_type in frame_types() {
+            for sequence_no in 0u8..8 {
+                let control = Control {
+                    frame_type: frame_type.clone(),
+                    sequence_no,
+                };
+                let mut bytes = Vec::new();
+                encode(control, &payload, &mut bytes).unwrap();
+
+                let mut out = Vec::new();
+                let decoded = decode(&bytes, &mut out).unwrap();
+
+                assert_eq!(decoded.sequence_no, sequence_no);
+                assert_eq!(decoded.frame_type, frame_type);
+                assert_eq!(out, payload);
+            }
+        }
+    }
+
+    #[test]
+    fn slice_sink_overflow_returns_buffer_overflow() {
+        let control = Control {
+            frame_type: FrameType::Data,
+            sequence_no: 0,
+        };
+        let mut bytes = Vec::new();
+        encode(control, &[1, 2, 3, 4], &mut bytes).unwrap();
+
+        let mut buf = [0u8; 1];
+        let mut sink = SliceSink::new(&mut buf);
+        assert!(matches!(
+            decode(&bytes, &mut sink),
+            Err(Error::BufferOverflow(Overflow))
+        ));
+    }
+
+    #[test]
+    fn streaming_decoder_agrees_with_one_shot_decode() {
+        let payload = [1u8, FLAG_SEQUENCE, CONTROL_ESCAPE, 9];
+        let control = Control {
+            frame_type: FrameType::Data,
+            sequence_no: 5,
+        };
+        let mut bytes = Vec::new();
+        encode(control, &payload, &mut bytes).unwrap();
+
+        let mut one_shot_out = Vec::new();
+        let one_shot = decode(&bytes, &mut one_shot_out).unwrap();
+
+        let mut decoder = Decoder::new(Vec::new());
+        let mut streamed = None;
+        for &byte in &bytes {
+            if let Some(result) = decoder.push(byte) {
+                let (control, payload) = result.unwrap();
+                streamed = Some((control, payload.to_vec()));
+            }
+        }
+        let (streamed_control, streamed_payload) = streamed.unwrap();
+
+        assert_eq!(one_shot.sequence_no, streamed_control.sequence_no);
+        assert_eq!(one_shot.frame_type, streamed_control.frame_type);
+        assert_eq!(one_shot_out, streamed_payload);
+    }
 }
\ No newline at end of file